@@ -0,0 +1,126 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use gtfs_structures::{Calendar, CalendarDate, Exception};
+use std::collections::HashSet;
+
+/// Expands a weekly `Calendar` pattern plus its existing `calendar_dates`
+/// exceptions into the explicit set of dates the service actually runs on.
+pub fn expand_calendar(calendar: &Calendar, exceptions: &[CalendarDate]) -> HashSet<NaiveDate> {
+    let mut dates = HashSet::new();
+
+    let mut date = calendar.start_date;
+    while date <= calendar.end_date {
+        if day_is_active(calendar, date.weekday()) {
+            dates.insert(date);
+        }
+        date = date.succ_opt().unwrap();
+    }
+
+    for exception in exceptions {
+        match exception.exception_type {
+            Exception::Added => {
+                dates.insert(exception.date);
+            }
+            Exception::Deleted => {
+                dates.remove(&exception.date);
+            }
+        }
+    }
+
+    dates
+}
+
+fn day_is_active(calendar: &Calendar, weekday: Weekday) -> bool {
+    match weekday {
+        Weekday::Mon => calendar.monday,
+        Weekday::Tue => calendar.tuesday,
+        Weekday::Wed => calendar.wednesday,
+        Weekday::Thu => calendar.thursday,
+        Weekday::Fri => calendar.friday,
+        Weekday::Sat => calendar.saturday,
+        Weekday::Sun => calendar.sunday,
+    }
+}
+
+/// Compresses an explicit set of active dates into the minimal
+/// `(Calendar, Vec<CalendarDate>)` pair GTFS allows: a weekly pattern plus
+/// the exceptions needed to reconcile it with `active_dates`.
+///
+/// For each weekday in `[start_date, end_date]`, the weekly boolean is set
+/// when the day is active on the majority of its occurrences in the range.
+/// Every date where that majority pattern disagrees with `active_dates` is
+/// then emitted as an exception: type 1 (added) when the pattern says
+/// "off" but the date is active, type 2 (removed) when the pattern says
+/// "on" but the date is inactive.
+pub fn compress_to_calendar(
+    service_id: String,
+    active_dates: &HashSet<NaiveDate>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> (Calendar, Vec<CalendarDate>) {
+    let mut active_occurrences: [u32; 7] = [0; 7];
+    let mut total_occurrences: [u32; 7] = [0; 7];
+
+    let mut date = start_date;
+    while date <= end_date {
+        let day = date.weekday().num_days_from_monday() as usize;
+        total_occurrences[day] += 1;
+        if active_dates.contains(&date) {
+            active_occurrences[day] += 1;
+        }
+        date = date.succ_opt().unwrap();
+    }
+
+    let is_majority = |day: usize| active_occurrences[day] * 2 > total_occurrences[day];
+
+    let calendar = Calendar {
+        id: service_id,
+        monday: is_majority(0),
+        tuesday: is_majority(1),
+        wednesday: is_majority(2),
+        thursday: is_majority(3),
+        friday: is_majority(4),
+        saturday: is_majority(5),
+        sunday: is_majority(6),
+        start_date,
+        end_date,
+    };
+
+    let mut exceptions = vec![];
+    let mut date = start_date;
+    while date <= end_date {
+        let pattern_says_active = day_is_active(&calendar, date.weekday());
+        let is_active = active_dates.contains(&date);
+
+        if is_active && !pattern_says_active {
+            exceptions.push(CalendarDate {
+                service_id: calendar.id.clone(),
+                date,
+                exception_type: Exception::Added,
+            });
+        } else if !is_active && pattern_says_active {
+            exceptions.push(CalendarDate {
+                service_id: calendar.id.clone(),
+                date,
+                exception_type: Exception::Deleted,
+            });
+        }
+
+        date = date.succ_opt().unwrap();
+    }
+
+    (calendar, exceptions)
+}
+
+/// Cancels every date a service currently runs on by emitting a type-2
+/// (removed) exception for each one, which overrides the published
+/// `calendar.txt` row without having to rewrite or drop it.
+pub fn cancel_service(calendar: &Calendar, exceptions: &[CalendarDate]) -> Vec<CalendarDate> {
+    expand_calendar(calendar, exceptions)
+        .into_iter()
+        .map(|date| CalendarDate {
+            service_id: calendar.id.clone(),
+            date,
+            exception_type: Exception::Deleted,
+        })
+        .collect()
+}
@@ -0,0 +1,157 @@
+use chrono::NaiveDate;
+use gtfs_structures::{Calendar, CalendarDate, Exception};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// One schedule correction: the desired weekly running pattern for a trip,
+/// matched either by its exact short name or by a regex against the
+/// route's long name, plus optional explicit add/remove dates for one-off
+/// exceptions the weekly pattern can't express.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixRule {
+    #[serde(default)]
+    pub trip_short_name: Option<String>,
+    #[serde(default)]
+    pub route_long_name_pattern: Option<String>,
+    pub monday: bool,
+    pub tuesday: bool,
+    pub wednesday: bool,
+    pub thursday: bool,
+    pub friday: bool,
+    pub saturday: bool,
+    pub sunday: bool,
+    #[serde(default)]
+    pub add_dates: Vec<NaiveDate>,
+    #[serde(default)]
+    pub remove_dates: Vec<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FixRulesFile {
+    rules: Vec<FixRule>,
+}
+
+/// A hot-reloadable catalog of schedule-fix rules. Rules are loaded once
+/// at startup and re-read whenever the backing file's modified timestamp
+/// advances, the same way a routes-scan struct decides its cached data is
+/// stale, so an operator can adjust corrections between runs without a
+/// recompile.
+pub struct FixRuleCatalog {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+    by_trip_short_name: HashMap<String, FixRule>,
+    by_route_long_name: Vec<(Regex, FixRule)>,
+}
+
+impl FixRuleCatalog {
+    pub fn load(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let mut catalog = Self {
+            path: path.into(),
+            last_modified: None,
+            by_trip_short_name: HashMap::new(),
+            by_route_long_name: Vec::new(),
+        };
+        catalog.reload_if_changed()?;
+        Ok(catalog)
+    }
+
+    /// Re-reads the rules file if its modified timestamp has advanced
+    /// since the last load. Returns `Ok(true)` if the rules were reloaded.
+    pub fn reload_if_changed(&mut self) -> std::io::Result<bool> {
+        let modified = std::fs::metadata(&self.path)?.modified()?;
+
+        if matches!(self.last_modified, Some(last) if modified <= last) {
+            return Ok(false);
+        }
+
+        let contents = std::fs::read_to_string(&self.path)?;
+        let file: FixRulesFile = serde_json::from_str(&contents)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+
+        let mut by_trip_short_name = HashMap::new();
+        let mut by_route_long_name = Vec::new();
+
+        for rule in file.rules {
+            if let Some(trip_short_name) = &rule.trip_short_name {
+                by_trip_short_name.insert(trip_short_name.clone(), rule.clone());
+            }
+            if let Some(pattern) = &rule.route_long_name_pattern {
+                let regex = Regex::new(pattern).map_err(|err| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+                })?;
+                by_route_long_name.push((regex, rule.clone()));
+            }
+        }
+
+        self.by_trip_short_name = by_trip_short_name;
+        self.by_route_long_name = by_route_long_name;
+        self.last_modified = Some(modified);
+
+        Ok(true)
+    }
+
+    fn rule_for_trip(&self, trip_short_name: &str, route_long_name: &str) -> Option<&FixRule> {
+        self.by_trip_short_name.get(trip_short_name).or_else(|| {
+            self.by_route_long_name
+                .iter()
+                .find(|(pattern, _)| pattern.is_match(route_long_name))
+                .map(|(_, rule)| rule)
+        })
+    }
+
+    /// Looks up the correction for a trip and builds the replacement
+    /// `Calendar`, applying the same `catenary-<name>-<trip>` id
+    /// convention the original hardcoded rules used.
+    pub fn calendar_for_trip(
+        &self,
+        trip_id: &str,
+        trip_short_name: &str,
+        route_long_name: &str,
+        calendar: &Calendar,
+    ) -> Option<Calendar> {
+        let rule = self.rule_for_trip(trip_short_name, route_long_name)?;
+
+        Some(Calendar {
+            id: format!("catenary-{}-{}", trip_short_name, trip_id),
+            monday: rule.monday,
+            tuesday: rule.tuesday,
+            wednesday: rule.wednesday,
+            thursday: rule.thursday,
+            friday: rule.friday,
+            saturday: rule.saturday,
+            sunday: rule.sunday,
+            start_date: calendar.start_date,
+            end_date: calendar.end_date,
+        })
+    }
+
+    /// Explicit add/remove date overrides configured for a trip,
+    /// translated into `calendar_dates` exceptions against `service_id`.
+    pub fn exceptions_for_trip(
+        &self,
+        trip_short_name: &str,
+        route_long_name: &str,
+        service_id: &str,
+    ) -> Vec<CalendarDate> {
+        let Some(rule) = self.rule_for_trip(trip_short_name, route_long_name) else {
+            return vec![];
+        };
+
+        rule.add_dates
+            .iter()
+            .map(|&date| CalendarDate {
+                service_id: service_id.to_string(),
+                date,
+                exception_type: Exception::Added,
+            })
+            .chain(rule.remove_dates.iter().map(|&date| CalendarDate {
+                service_id: service_id.to_string(),
+                date,
+                exception_type: Exception::Deleted,
+            }))
+            .collect()
+    }
+}
@@ -0,0 +1,135 @@
+use gtfs_structures::{Frequency, RawStopTime, RawTrip};
+use std::collections::{HashMap, HashSet};
+
+/// Expands every headway-based `frequencies.txt` record into concrete
+/// trips, replacing each template trip with one real trip per scheduled
+/// departure. Trips with no matching frequency record pass through
+/// unchanged.
+pub fn expand_trips_and_stop_times(
+    trips: Vec<RawTrip>,
+    stop_times: Vec<RawStopTime>,
+    frequencies: &[Frequency],
+) -> (Vec<RawTrip>, Vec<RawStopTime>) {
+    if frequencies.is_empty() {
+        return (trips, stop_times);
+    }
+
+    let mut stop_times_by_trip: HashMap<String, Vec<RawStopTime>> = HashMap::new();
+    for stop_time in stop_times {
+        stop_times_by_trip
+            .entry(stop_time.trip_id.clone())
+            .or_default()
+            .push(stop_time);
+    }
+
+    let frequency_trip_ids: HashSet<&str> =
+        frequencies.iter().map(|frequency| frequency.trip_id.as_str()).collect();
+
+    let mut expanded_trips = vec![];
+    let mut expanded_stop_times = vec![];
+
+    for trip in &trips {
+        if !frequency_trip_ids.contains(trip.id.as_str()) {
+            expanded_trips.push(trip.clone());
+            if let Some(times) = stop_times_by_trip.get(&trip.id) {
+                expanded_stop_times.extend(times.clone());
+            }
+        }
+    }
+
+    // GTFS allows more than one frequency row per trip_id (e.g. a
+    // different headway at peak vs. off-peak); track how many rows we've
+    // already expanded for a given trip_id so their generated trip ids
+    // don't collide.
+    let mut frequency_row_index: HashMap<&str, usize> = HashMap::new();
+
+    for frequency in frequencies {
+        let Some(template_trip) = trips.iter().find(|trip| trip.id == frequency.trip_id) else {
+            continue;
+        };
+        let Some(template_stop_times) = stop_times_by_trip.get(&frequency.trip_id) else {
+            continue;
+        };
+
+        let counter = frequency_row_index
+            .entry(frequency.trip_id.as_str())
+            .or_insert(0);
+        let row_index = *counter;
+        *counter += 1;
+
+        let (trips_for_frequency, stop_times_for_frequency) =
+            expand_frequency(frequency, row_index, template_trip, template_stop_times);
+
+        expanded_trips.extend(trips_for_frequency);
+        expanded_stop_times.extend(stop_times_for_frequency);
+    }
+
+    (expanded_trips, expanded_stop_times)
+}
+
+/// Materializes concrete trips from a single frequency record, stepping
+/// from `start_time` to `end_time` in `headway_secs` increments and
+/// shifting every stop time of the template trip by the offset from its
+/// own first departure, so each materialized trip keeps the template's
+/// run time but departs at its own real clock time. `row_index` is this
+/// record's position among the (possibly several) frequency rows sharing
+/// `frequency.trip_id`, folded into the generated trip id so rows don't
+/// clobber each other's output.
+fn expand_frequency(
+    frequency: &Frequency,
+    row_index: usize,
+    template_trip: &RawTrip,
+    template_stop_times: &[RawStopTime],
+) -> (Vec<RawTrip>, Vec<RawStopTime>) {
+    let Some(first_departure) = template_stop_times
+        .first()
+        .and_then(|stop_time| stop_time.departure_time)
+    else {
+        return (vec![], vec![]);
+    };
+
+    // `exact_times` tells downstream realtime matching whether these
+    // departures are clock-face exact (`ScheduleBased`) or only an average
+    // headway (`FrequencyBased`, GTFS's default when the column is empty).
+    // It doesn't change how the static feed is materialized — both kinds
+    // step from `start_time` to `end_time` by a fixed `headway_secs` — so
+    // we fold it into the generated trip id rather than branching on it,
+    // to keep schedule-based and approximate expansions distinguishable.
+    let is_schedule_based = matches!(
+        frequency.exact_times,
+        Some(gtfs_structures::ExactTimes::ScheduleBased)
+    );
+    let kind = if is_schedule_based { "sched" } else { "freq" };
+
+    let mut trips = vec![];
+    let mut stop_times = vec![];
+
+    let mut departure = frequency.start_time;
+    let mut instance = 0;
+
+    while departure < frequency.end_time {
+        let offset = departure as i64 - first_departure as i64;
+        let trip_id = format!("{}-{}-{}-{}", frequency.trip_id, kind, row_index, instance);
+
+        let mut trip = template_trip.clone();
+        trip.id = trip_id.clone();
+        trips.push(trip);
+
+        for stop_time in template_stop_times {
+            let mut stop_time = stop_time.clone();
+            stop_time.trip_id = trip_id.clone();
+            stop_time.arrival_time = stop_time
+                .arrival_time
+                .map(|time| (time as i64 + offset) as u32);
+            stop_time.departure_time = stop_time
+                .departure_time
+                .map(|time| (time as i64 + offset) as u32);
+            stop_times.push(stop_time);
+        }
+
+        departure += frequency.headway_secs;
+        instance += 1;
+    }
+
+    (trips, stop_times)
+}
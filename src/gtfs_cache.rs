@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Cached metadata from the last successful GTFS download, used to make a
+/// conditional request instead of always re-downloading the full zip.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DownloadCacheState {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub byte_size: u64,
+}
+
+impl DownloadCacheState {
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents)
+    }
+}
+
+/// Sends a conditional GET for the GTFS feed using the previous response's
+/// `ETag`/`Last-Modified` headers. Returns `None` when the server answers
+/// 304 Not Modified, meaning the already-extracted feed is still current,
+/// or `Some` with the downloaded bytes and the fresh cache state to
+/// persist after extraction.
+pub async fn conditional_fetch(
+    client: &reqwest::Client,
+    url: &str,
+    previous: &DownloadCacheState,
+) -> Result<Option<(bytes::Bytes, DownloadCacheState)>, reqwest::Error> {
+    let mut request = client.get(url);
+
+    if let Some(etag) = &previous.etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &previous.last_modified {
+        request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = request.send().await?;
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+    let last_modified = response
+        .headers()
+        .get(reqwest::header::LAST_MODIFIED)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+
+    let data = response.bytes().await?;
+
+    let state = DownloadCacheState {
+        etag,
+        last_modified,
+        byte_size: data.len() as u64,
+    };
+
+    Ok(Some((data, state)))
+}
@@ -10,7 +10,13 @@ use std::io::Write;
 use std::path::PathBuf;
 use std::str::FromStr;
 
+mod calendar_dates;
+mod fix_rules;
+mod frequencies;
+mod gtfs_cache;
+mod route_validation;
 mod routes_list;
+mod service_day;
 
 const GTFS_URL: &str = "https://content.amtrak.com/content/gtfs/GTFS.zip";
 
@@ -22,42 +28,82 @@ async fn get_route_data(
 ) {
 }
 
-const DOWNLOAD_AND_UNZIP_INIT: bool = true;
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
     let client = reqwest::Client::new();
-    let mut dest = File::create("./amtrak-gtfs.zip")?;
 
     let target_dir = PathBuf::from("./amtrak-gtfs");
+    let cache_state_path = PathBuf::from("./amtrak-gtfs-cache.json");
 
-    if DOWNLOAD_AND_UNZIP_INIT {
-        let response = client.get(GTFS_URL).send().await?;
+    let previous_cache_state = gtfs_cache::DownloadCacheState::load(&cache_state_path);
 
-        println!("Finished downloading Amtrak GTFS file");
+    match gtfs_cache::conditional_fetch(&client, GTFS_URL, &previous_cache_state).await? {
+        Some((data, new_cache_state)) => {
+            println!("Finished downloading Amtrak GTFS file");
 
-        let data = response.bytes().await?;
+            let download_byte_size = ByteSize::b(data.len() as u64);
+            println!("{:?} downloaded", download_byte_size);
 
-        let download_byte_size = ByteSize::b(data.len() as u64);
-        println!("{:?} downloaded", download_byte_size);
+            let mut dest = File::create("./amtrak-gtfs.zip")?;
+            dest.write_all(data.as_ref())?;
 
-        dest.write_all(data.as_ref())?;
+            let mut zipped_file = File::open("./amtrak-gtfs.zip")?;
 
-        let mut zipped_file = File::open("./amtrak-gtfs.zip")?;
+            let mut buf: Vec<u8> = vec![];
 
-        let mut buf: Vec<u8> = vec![];
+            // read bytes and pass back error if unable to read
+            let read = zipped_file.read_to_end(&mut buf)?;
 
-        // read bytes and pass back error if unable to read
-        let read = zipped_file.read_to_end(&mut buf)?;
+            zip_extract::extract(Cursor::new(buf), &target_dir, true)?;
 
-        zip_extract::extract(Cursor::new(buf), &target_dir, true)?;
+            new_cache_state.save(&cache_state_path)?;
+        }
+        None => {
+            println!(
+                "Amtrak GTFS feed unchanged since last run (304 Not Modified), reusing existing extract"
+            );
+        }
     }
 
-    //fetch the amtrak route list from their website
+    let mut fix_rules = fix_rules::FixRuleCatalog::load("./fix_rules.json")?;
+
+    let frequencies_raw = gtfs_structures::RawGtfs::from_path(&target_dir)?;
+
+    if let Some(frequencies) = frequencies_raw
+        .frequencies
+        .map(|result| result.unwrap())
+        .filter(|frequencies| !frequencies.is_empty())
+    {
+        println!(
+            "Expanding {} frequency-based trip(s) into explicit trips",
+            frequencies.len()
+        );
+
+        let (expanded_trips, expanded_stop_times) = frequencies::expand_trips_and_stop_times(
+            frequencies_raw.trips.unwrap(),
+            frequencies_raw.stop_times.unwrap(),
+            &frequencies,
+        );
+
+        let mut expanded_trip_wtr = csv::Writer::from_path("./amtrak-gtfs/trips.txt")?;
+        for trip in expanded_trips {
+            expanded_trip_wtr.serialize(trip)?;
+        }
 
-    //let routes_list_from_website = routes_list::fetch_and_decode_routes(client.clone()).await?;
+        let mut expanded_stop_time_wtr = csv::Writer::from_path("./amtrak-gtfs/stop_times.txt")?;
+        for stop_time in expanded_stop_times {
+            expanded_stop_time_wtr.serialize(stop_time)?;
+        }
 
-    //println!("{} routes found on their website", routes_list_from_website.len());
+        // The template trip ids frequencies.txt pointed at no longer exist
+        // now that they've been materialized into explicit trips, so the
+        // file would otherwise dangle and make the rewritten feed invalid.
+        if let Err(err) = std::fs::remove_file("./amtrak-gtfs/frequencies.txt") {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                return Err(err.into());
+            }
+        }
+    }
 
     println!("Reading official GTFS file");
 
@@ -65,6 +111,53 @@ async fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
 
     println!("Read took {:?}", gtfs_initial_read.read_duration);
 
+    //fetch the amtrak route list from their website and cross-validate it
+    //against the GTFS feed, so a route the website has published but the
+    //feed hasn't caught up with yet gets flagged instead of silently missed.
+    //This is a validation side-feature, not part of the core fix, so a
+    //failure here (the website being down, a JSON schema change) must not
+    //abort the run before the trip/calendar corrections below are written.
+    match routes_list::fetch_and_decode_routes(client.clone()).await {
+        Ok(routes_list_from_website) => {
+            println!(
+                "{} routes found on their website",
+                routes_list_from_website.len()
+            );
+
+            let feed_route_long_names: HashSet<String> = gtfs_initial_read
+                .routes
+                .values()
+                .filter_map(|route| route.long_name.clone())
+                .collect();
+
+            let route_discrepancy_report =
+                route_validation::cross_validate(&routes_list_from_website, &feed_route_long_names);
+
+            println!(
+                "Route validation: {} missing from feed, {} missing from website, {} mismatched name(s)",
+                route_discrepancy_report.missing_from_feed.len(),
+                route_discrepancy_report.missing_from_website.len(),
+                route_discrepancy_report.mismatched_names.len()
+            );
+
+            match serde_json::to_string_pretty(&route_discrepancy_report) {
+                Ok(report_json) => {
+                    if let Err(err) = std::fs::write("./route_discrepancy_report.json", report_json)
+                    {
+                        eprintln!("Failed to write route discrepancy report: {}", err);
+                    }
+                }
+                Err(err) => eprintln!("Failed to serialize route discrepancy report: {}", err),
+            }
+        }
+        Err(err) => {
+            eprintln!(
+                "Skipping route validation: failed to fetch Amtrak's routes-list.json: {}",
+                err
+            );
+        }
+    }
+
     let mut possible_trip_ids_to_fix: Vec<String> = vec![];
 
     let mut surfliner_services_to_cancel: Vec<String> = vec![];
@@ -98,14 +191,11 @@ async fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
                 .unwrap();
 
             if initial_timezone != chrono_tz::Tz::America__New_York {
-                let soonest_hr_to_break = match initial_timezone {
-                    chrono_tz::Tz::America__Chicago => 1,
-                    chrono_tz::Tz::America__Denver => 2,
-                    chrono_tz::Tz::America__Los_Angeles => 3,
-                    _ => unreachable!(),
-                };
-
-                if departure_from_midnight <= (soonest_hr_to_break * 3600) {
+                if service_day::is_trip_broken(
+                    service.start_date,
+                    initial_timezone,
+                    departure_from_midnight,
+                ) {
                     println!(
                         "Potentially broken: {} {} to {}",
                         trip.trip_short_name.as_ref().unwrap(),
@@ -145,8 +235,26 @@ async fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
 
     let mut trip_wtr = csv::Writer::from_path("./amtrak-gtfs/trips.txt")?;
     let mut calendar_wtr = csv::Writer::from_path("./amtrak-gtfs/calendar.txt")?;
+    let mut calendar_dates_wtr = csv::Writer::from_path("./amtrak-gtfs/calendar_dates.txt")?;
 
     let mut calendars_to_write = gtfs_raw.calendar.unwrap().unwrap();
+    let mut calendar_dates_to_write = gtfs_raw
+        .calendar_dates
+        .map(|result| result.unwrap())
+        .unwrap_or_default();
+
+    for service_id in &surfliner_services_to_cancel {
+        if let Some(calendar) = gtfs_initial_read.calendar.get(service_id.as_str()) {
+            let existing_exceptions = gtfs_initial_read
+                .calendar_dates
+                .get(service_id.as_str())
+                .map(|exceptions| exceptions.as_slice())
+                .unwrap_or(&[]);
+
+            calendar_dates_to_write
+                .extend(calendar_dates::cancel_service(calendar, existing_exceptions));
+        }
+    }
 
     let trips_to_process = gtfs_raw.trips.unwrap();
 
@@ -156,16 +264,58 @@ async fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
         let calendar = gtfs_initial_read.calendar.get(&trip.service_id).unwrap();
 
         if possible_trip_ids_to_fix.contains(&trip.id) {
-            let new_calendar = make_calendar_for_trip_short_name(
-                &trip.id,
-                &trip.trip_short_name.as_ref().unwrap(),
-                calendar.clone(),
-            );
+            // Pick up edits to the rules file between runs (and, for a
+            // long GTFS feed, even mid-run) without a recompile.
+            fix_rules.reload_if_changed()?;
+
+            let trip_short_name = trip.trip_short_name.as_ref().unwrap();
+            let route_long_name = gtfs_initial_read
+                .routes
+                .get(trip.route_id.as_str())
+                .unwrap()
+                .long_name
+                .as_ref()
+                .unwrap();
+
+            let new_calendar =
+                fix_rules.calendar_for_trip(&trip.id, trip_short_name, route_long_name, calendar);
 
             if let Some(new_calendar) = new_calendar {
-                trip.service_id = new_calendar.id.clone();
+                // Expand the rule's weekly pattern to an explicit active-date
+                // set, layer the rule's add/remove overrides on top of it,
+                // then compress back down to the minimal weekly pattern +
+                // exceptions pair GTFS allows, instead of writing the rule's
+                // weekly booleans straight through.
+                let mut active_dates =
+                    calendar_dates::expand_calendar(&new_calendar, &[]);
+
+                for exception in fix_rules.exceptions_for_trip(
+                    trip_short_name,
+                    route_long_name,
+                    &new_calendar.id,
+                ) {
+                    match exception.exception_type {
+                        gtfs_structures::Exception::Added => {
+                            active_dates.insert(exception.date);
+                        }
+                        gtfs_structures::Exception::Deleted => {
+                            active_dates.remove(&exception.date);
+                        }
+                    }
+                }
+
+                let (compressed_calendar, exceptions) = calendar_dates::compress_to_calendar(
+                    new_calendar.id.clone(),
+                    &active_dates,
+                    new_calendar.start_date,
+                    new_calendar.end_date,
+                );
+
+                calendar_dates_to_write.extend(exceptions);
+
+                trip.service_id = compressed_calendar.id.clone();
 
-                calendars_to_write.push(new_calendar);
+                calendars_to_write.push(compressed_calendar);
             }
         }
 
@@ -177,55 +327,11 @@ async fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
         calendar_wtr.serialize(calendar_raw);
     }
 
-    Ok(())
-}
-
-fn make_calendar_for_trip_short_name(
-    trip_id: &str,
-    trip_short_name: &str,
-    calendar: gtfs_structures::Calendar,
-) -> Option<gtfs_structures::Calendar> {
-    let id = format!("catenary-{}-{}", trip_short_name, trip_id);
-
-    match trip_short_name {
-        "2" => Some(gtfs_structures::Calendar {
-            id,
-            monday: true,
-            tuesday: false,
-            wednesday: false,
-            thursday: true,
-            friday: false,
-            saturday: true,
-            sunday: false,
-            start_date: calendar.start_date,
-            end_date: calendar.end_date,
-        }),
-        "343" => Some(gtfs_structures::Calendar {
-            id,
-            monday: false,
-            tuesday: false,
-            wednesday: false,
-            thursday: false,
-            friday: false,
-            saturday: true,
-            sunday: false,
-            start_date: calendar.start_date,
-            end_date: calendar.end_date,
-        }),
-        "422" => Some(gtfs_structures::Calendar {
-            id,
-            monday: true,
-            tuesday: false,
-            wednesday: false,
-            thursday: true,
-            friday: false,
-            saturday: true,
-            sunday: false,
-            start_date: calendar.start_date,
-            end_date: calendar.end_date,
-        }),
-        _ => None,
+    for calendar_date in calendar_dates_to_write {
+        calendar_dates_wtr.serialize(calendar_date);
     }
+
+    Ok(())
 }
 
 fn calendar_to_string_to_add(calendar: &gtfs_structures::Calendar) -> String {
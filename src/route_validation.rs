@@ -0,0 +1,73 @@
+use crate::routes_list::AmtrakRouteInfo;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// A route whose name appears, in some form, on both the website and in
+/// the feed, but not identically — the two names differ only in letter
+/// case or in leading/trailing whitespace.
+#[derive(Debug, Serialize)]
+pub struct MismatchedRouteName {
+    pub website_name: String,
+    pub feed_name: String,
+}
+
+/// The result of joining Amtrak's published route list against the GTFS
+/// feed's routes by long name.
+#[derive(Debug, Serialize)]
+pub struct RouteDiscrepancyReport {
+    pub missing_from_feed: Vec<String>,
+    pub missing_from_website: Vec<String>,
+    pub mismatched_names: Vec<MismatchedRouteName>,
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Joins the website's `routeName` list against the GTFS `routes` by long
+/// name, reporting routes advertised on the website that haven't yet
+/// appeared in the feed (and vice versa), plus names that matched only
+/// after trimming surrounding whitespace and ignoring case (punctuation
+/// or inner-whitespace differences are not matched), so the fixer doubles as a
+/// feed-completeness checker.
+pub fn cross_validate(
+    website_routes: &[AmtrakRouteInfo],
+    feed_route_long_names: &HashSet<String>,
+) -> RouteDiscrepancyReport {
+    let normalized_feed_names: HashMap<String, &str> = feed_route_long_names
+        .iter()
+        .map(|name| (normalize(name), name.as_str()))
+        .collect();
+
+    let mut missing_from_feed = vec![];
+    let mut mismatched_names = vec![];
+    let mut matched_feed_names: HashSet<&str> = HashSet::new();
+
+    for website_route in website_routes {
+        let website_name = website_route.route_name.as_str();
+
+        if feed_route_long_names.contains(website_name) {
+            matched_feed_names.insert(website_name);
+        } else if let Some(feed_name) = normalized_feed_names.get(&normalize(website_name)) {
+            matched_feed_names.insert(feed_name);
+            mismatched_names.push(MismatchedRouteName {
+                website_name: website_name.to_string(),
+                feed_name: feed_name.to_string(),
+            });
+        } else {
+            missing_from_feed.push(website_name.to_string());
+        }
+    }
+
+    let missing_from_website = feed_route_long_names
+        .iter()
+        .filter(|name| !matched_feed_names.contains(name.as_str()))
+        .cloned()
+        .collect();
+
+    RouteDiscrepancyReport {
+        missing_from_feed,
+        missing_from_website,
+        mismatched_names,
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Converts a GTFS `stop_time` (seconds since "noon minus twelve hours" of
+/// `service_date`) into the absolute UTC instant it represents in `tz`.
+///
+/// The GTFS spec measures stop times relative to noon-minus-twelve instead
+/// of local midnight specifically because local noon is never ambiguous or
+/// skipped across a spring-forward/fall-back transition, so this reference
+/// point survives DST changes that would otherwise make "midnight" a
+/// fuzzy concept twice a year.
+pub fn stop_time_to_utc(service_date: NaiveDate, tz: Tz, seconds_since_ref: u32) -> DateTime<Utc> {
+    let local_noon = tz
+        .from_local_datetime(&service_date.and_hms_opt(12, 0, 0).unwrap())
+        .single()
+        .expect("local noon is never ambiguous or nonexistent across a DST transition");
+
+    let reference = local_noon.with_timezone(&Utc) - Duration::hours(12);
+
+    reference + Duration::seconds(seconds_since_ref as i64)
+}
+
+/// A trip is genuinely broken when the calendar date its first departure
+/// actually falls on, once correctly localized to the stop's own
+/// timezone, disagrees with the calendar date the feed's Eastern
+/// reference would put it on. Amtrak publishes every service day against
+/// an Eastern reference, so an early-morning departure at a western stop
+/// can localize to the previous calendar day even though `service_date`
+/// says otherwise — exactly the symptom the old per-timezone hour
+/// thresholds were trying to approximate with hardcoded constants.
+///
+/// Comparing the two UTC instants directly would always disagree (they're
+/// offset by the fixed difference between the zones for every `stop_time`
+/// value), so we compare the calendar dates those instants land on in the
+/// stop's own timezone instead.
+pub fn is_trip_broken(service_date: NaiveDate, stop_tz: Tz, seconds_since_ref: u32) -> bool {
+    let feed_assumed_utc = stop_time_to_utc(
+        service_date,
+        chrono_tz::Tz::America__New_York,
+        seconds_since_ref,
+    );
+    let actual_utc = stop_time_to_utc(service_date, stop_tz, seconds_since_ref);
+
+    let feed_assumed_local_date = feed_assumed_utc.with_timezone(&stop_tz).date_naive();
+    let actual_local_date = actual_utc.with_timezone(&stop_tz).date_naive();
+
+    feed_assumed_local_date != actual_local_date
+}